@@ -6,19 +6,28 @@ use anyhow::{bail, Result};
 use derive_more::Deref;
 use indexmap::IndexMap;
 use serde::de::DeserializeOwned;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tempfile::TempDir;
 use tokio::fs::{self, File};
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 
+/// Scratch root for per-run temp dirs (package staging, grammar checkouts). Wiped after every run.
+const BUILD_DIR: &str = "build";
+/// Persistent cache for downloaded toolchains (wasi-sdk, the WASI adapter). Survives runs.
+const CACHE_DIR: &str = "cache";
+/// Where finished `.tar.gz` packages are written. Survives runs.
+const DIST_DIR: &str = "dist";
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let should_publish = false;
+    let should_publish = should_publish();
 
     let extensions_toml: ExtensionsToml = read_toml_file("extensions.toml").await?;
 
-    fs::create_dir_all("build").await?;
+    fs::create_dir_all(BUILD_DIR).await?;
+    fs::create_dir_all(CACHE_DIR).await?;
+    fs::create_dir_all(DIST_DIR).await?;
 
     let extension_ids = if should_publish {
         unpublished_extension_ids(&extensions_toml).await?
@@ -41,16 +50,24 @@ async fn main() -> Result<()> {
             extension_id,
             &extension_info.path,
             &extension_info.version,
+            Path::new(DIST_DIR),
             should_publish,
         )
         .await?;
     }
 
-    fs::remove_dir_all("build").await?;
+    fs::remove_dir_all(BUILD_DIR).await?;
 
     Ok(())
 }
 
+/// Whether to publish unpublished extensions instead of just packaging changed ones.
+fn should_publish() -> bool {
+    std::env::var("ZED_EXTENSIONS_PUBLISH")
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Deserialize)]
 struct ExtensionId(String);
 
@@ -73,6 +90,7 @@ async fn package_extension(
     extension_id: ExtensionId,
     extension_path: &Path,
     extension_version: &str,
+    output_dir: &Path,
     should_publish: bool,
 ) -> Result<()> {
     let (metadata, _format) = read_extension_manifest(&extension_path).await?;
@@ -92,12 +110,18 @@ async fn package_extension(
         bail!(error)
     }
 
+    check_schema_version_compatibility(&metadata)?;
+
     let mut package_manifest = ExtensionManifest {
         name: metadata.name.clone(),
         version: metadata.version,
         description: metadata.description,
         repository: metadata.repository,
         authors: metadata.authors,
+        schema_version: Some(CURRENT_SCHEMA_VERSION),
+        min_schema_version: None,
+        max_schema_version: None,
+        wasm_api_version: None,
         lib: None,
         themes: Vec::new(),
         languages: Vec::new(),
@@ -105,9 +129,8 @@ async fn package_extension(
         language_servers: IndexMap::new(),
     };
 
-    let package_dir = tempfile::tempdir_in("build")?;
-    let archive_name = package_dir
-        .path()
+    let package_dir = tempfile::tempdir_in(BUILD_DIR)?;
+    let archive_name = output_dir
         .join(format!("{extension_id}-{}", package_manifest.version))
         .with_extension("tar.gz");
 
@@ -146,6 +169,270 @@ async fn package_extension(
         }
     }
 
+    if !metadata.grammars.is_empty() {
+        fs::create_dir(&grammars_pkg_dir).await?;
+
+        for (grammar_name, grammar_entry) in &metadata.grammars {
+            build_grammar(grammar_name, grammar_entry, &grammars_pkg_dir).await?;
+            package_manifest
+                .grammars
+                .insert(grammar_name.clone(), grammar_entry.clone());
+        }
+    }
+
+    if is_directory(&languages_src_dir).await {
+        fs::create_dir(&languages_pkg_dir).await?;
+
+        let mut read_dir = fs::read_dir(languages_src_dir).await?;
+        while let Some(language_entry) = read_dir.next_entry().await? {
+            if !language_entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let Some(language_dir_name) = language_entry
+                .file_name()
+                .to_str()
+                .map(|name| name.to_string())
+            else {
+                continue;
+            };
+
+            let language_path = package_language(
+                &language_dir_name,
+                &language_entry.path(),
+                &package_manifest.grammars,
+                &languages_pkg_dir,
+            )
+            .await?;
+
+            package_manifest.languages.push(language_path);
+        }
+    }
+
+    if let Some(lib) = &metadata.lib {
+        let (lib_path, wasm_api_version) =
+            build_extension_lib(extension_path, lib, package_dir.path()).await?;
+        package_manifest.wasm_api_version = Some(wasm_api_version);
+        package_manifest.lib = Some(LibManifestEntry {
+            path: lib_path
+                .strip_prefix(package_dir.path())?
+                .to_string_lossy()
+                .into_owned(),
+        });
+    }
+
+    let manifest_path = package_dir.path().join("manifest.json");
+    fs::write(&manifest_path, serde_json::to_vec_pretty(&package_manifest)?).await?;
+
+    write_tar_gz(package_dir.path(), &archive_name)?;
+
+    println!(
+        "Packaged '{extension_id}' ({}) to {}",
+        package_manifest.version,
+        archive_name.display()
+    );
+
+    if should_publish {
+        publish_extension(&extension_id, &package_manifest, &archive_name).await?;
+    }
+
+    Ok(())
+}
+
+const WASMTIME_RELEASE_TAG: &str = "v20.0.0";
+
+async fn ensure_wasm32_wasi_target() -> Result<()> {
+    let output = Command::new("rustup")
+        .args(&["target", "add", "wasm32-wasi"])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        bail!(
+            "Failed to install the wasm32-wasi target:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Downloads and caches the WASI preview1 reactor adapter under `cache/`.
+async fn download_wasi_adapter() -> Result<PathBuf> {
+    let adapter_path = Path::new(CACHE_DIR).join("wasi_snapshot_preview1.reactor.wasm");
+
+    if fs::try_exists(&adapter_path).await? {
+        return Ok(adapter_path);
+    }
+
+    let adapter_url = format!(
+        "https://github.com/bytecodealliance/wasmtime/releases/download/{WASMTIME_RELEASE_TAG}/wasi_snapshot_preview1.reactor.wasm"
+    );
+
+    let response = reqwest::get(&adapter_url).await?.error_for_status()?;
+    let adapter_bytes = response.bytes().await?;
+    fs::write(&adapter_path, &adapter_bytes).await?;
+
+    Ok(adapter_path)
+}
+
+/// Compiles the extension's `lib` crate to a wasm component, writing `extension.wasm` into
+/// `package_dir`.
+async fn build_extension_lib(
+    extension_path: &Path,
+    lib: &LibManifestEntry,
+    package_dir: &Path,
+) -> Result<(PathBuf, String)> {
+    let crate_path = extension_path.join(&lib.path);
+
+    ensure_wasm32_wasi_target().await?;
+
+    let output = Command::new("cargo")
+        .args(&["build", "--release", "--target", "wasm32-wasi"])
+        .current_dir(&crate_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        bail!(
+            "Failed to build extension lib at {}:\n{}",
+            crate_path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let crate_manifest: CargoManifest = read_toml_file(crate_path.join("Cargo.toml")).await?;
+    let wasm_api_version = resolve_wasm_api_version(&crate_path, &crate_manifest).await?;
+
+    let core_module_name = crate_manifest.package.name.replace('-', "_");
+    let core_module_path = crate_path
+        .join("target/wasm32-wasi/release")
+        .join(core_module_name)
+        .with_extension("wasm");
+    let core_module = fs::read(&core_module_path).await?;
+
+    let adapter_path = download_wasi_adapter().await?;
+    let adapter_bytes = fs::read(&adapter_path).await?;
+
+    let component_bytes = wit_component::ComponentEncoder::default()
+        .module(&core_module)?
+        .adapter("wasi_snapshot_preview1", &adapter_bytes)?
+        .encode()?;
+
+    let component_path = package_dir.join("extension.wasm");
+    fs::write(&component_path, component_bytes).await?;
+
+    Ok((component_path, wasm_api_version))
+}
+
+/// Determines the `zed_extension_api` version an extension crate was linked against.
+async fn resolve_wasm_api_version(
+    crate_path: &Path,
+    crate_manifest: &CargoManifest,
+) -> Result<String> {
+    if let Some(version) = crate_manifest
+        .package
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.zed_api_version.clone())
+    {
+        return Ok(version);
+    }
+
+    let lock_path = crate_path.join("Cargo.lock");
+    let cargo_lock: CargoLock = read_toml_file(&lock_path).await.map_err(|_| {
+        anyhow::anyhow!(
+            "Extension lib at {} has no Cargo.lock and no package.metadata.zed_api_version hint; \
+             could not determine its zed_extension_api version",
+            crate_path.display()
+        )
+    })?;
+
+    cargo_lock
+        .package
+        .into_iter()
+        .find(|package| package.name == "zed_extension_api")
+        .map(|package| package.version)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Extension lib at {} does not depend on zed_extension_api; could not determine \
+                 its wasm API version",
+                crate_path.display()
+            )
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoManifest {
+    package: CargoPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackage {
+    name: String,
+    #[serde(default)]
+    metadata: Option<CargoPackageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackageMetadata {
+    #[serde(default)]
+    zed_api_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLock {
+    package: Vec<CargoLockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLockPackage {
+    name: String,
+    version: String,
+}
+
+fn write_tar_gz(source_dir: &Path, archive_path: &Path) -> Result<()> {
+    let tar_gz_file = std::fs::File::create(archive_path)?;
+    let encoder = flate2::write::GzEncoder::new(tar_gz_file, flate2::Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    archive.append_dir_all(".", source_dir)?;
+    archive.finish()?;
+
+    Ok(())
+}
+
+/// The schema version this packager produces extension packages for.
+const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Bails if the extension declares an unsupported schema version (or min/max range).
+fn check_schema_version_compatibility(metadata: &ExtensionManifest) -> Result<()> {
+    if let Some(schema_version) = metadata.schema_version {
+        if schema_version > CURRENT_SCHEMA_VERSION {
+            bail!(
+                "Extension '{}' declares schema_version {schema_version}, but this packager only supports up to {CURRENT_SCHEMA_VERSION}",
+                metadata.name
+            );
+        }
+    }
+
+    if let Some(min_schema_version) = metadata.min_schema_version {
+        if min_schema_version > CURRENT_SCHEMA_VERSION {
+            bail!(
+                "Extension '{}' requires min_schema_version {min_schema_version}, but this packager only supports up to {CURRENT_SCHEMA_VERSION}",
+                metadata.name
+            );
+        }
+    }
+
+    if let Some(max_schema_version) = metadata.max_schema_version {
+        if max_schema_version < CURRENT_SCHEMA_VERSION {
+            bail!(
+                "Extension '{}' requires max_schema_version {max_schema_version}, but this packager produces schema version {CURRENT_SCHEMA_VERSION}",
+                metadata.name
+            );
+        }
+    }
+
     Ok(())
 }
 
@@ -156,7 +443,7 @@ async fn is_directory(path: impl AsRef<Path>) -> bool {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct ExtensionManifest {
     // pub id: String,
     pub name: String,
@@ -168,6 +455,14 @@ pub struct ExtensionManifest {
     #[serde(default)]
     pub authors: Vec<String>,
     #[serde(default)]
+    pub schema_version: Option<u32>,
+    #[serde(default)]
+    pub min_schema_version: Option<u32>,
+    #[serde(default)]
+    pub max_schema_version: Option<u32>,
+    #[serde(default)]
+    pub wasm_api_version: Option<String>,
+    #[serde(default)]
     pub lib: Option<LibManifestEntry>,
     #[serde(default)]
     pub themes: Vec<PathBuf>,
@@ -179,19 +474,19 @@ pub struct ExtensionManifest {
     pub language_servers: IndexMap<String, LanguageServerManifestEntry>,
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct LibManifestEntry {
     path: String,
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct GrammarManifestEntry {
     repository: String,
     #[serde(alias = "commit")]
     rev: String,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct LanguageServerManifestEntry {
     name: String,
     language: String,
@@ -267,25 +562,125 @@ fn validate_theme(theme: &serde_json::Value) -> Result<()> {
     Ok(())
 }
 
-async fn get_published_versions_by_extension_id() -> Result<HashMap<ExtensionId, Vec<String>>> {
-    Ok(HashMap::new())
+fn validate_language_config(config: &serde_json::Value) -> Result<()> {
+    let json_schema: serde_json::Value =
+        serde_json::from_str(include_str!("../schemas/language-config.json"))?;
+
+    let mut scope = valico::json_schema::Scope::new();
+    let schema = scope.compile_and_return(json_schema, false)?;
+
+    let validation = schema.validate(config);
+    if !validation.errors.is_empty() {
+        bail!("Language config validation failed: {:?}", validation.errors);
+    }
+
+    Ok(())
 }
 
-/// Returns the list of IDs of extensions that need to be published.
-async fn unpublished_extension_ids(extensions_toml: &ExtensionsToml) -> Result<Vec<ExtensionId>> {
-    let published_extension_versions = get_published_versions_by_extension_id().await?;
+#[derive(Debug, Deserialize)]
+struct LanguageConfigToml {
+    #[serde(default)]
+    grammar: Option<String>,
+}
 
-    let mut unpublished = Vec::new();
-    for (extension_id, extension_info) in extensions_toml.iter() {
-        let Some(versions) = published_extension_versions.get(&extension_id) else {
+/// Bails if `language_config` references a grammar that isn't in `grammars`.
+fn check_referenced_grammar_exists(
+    language_name: &str,
+    language_config: &LanguageConfigToml,
+    grammars: &IndexMap<String, GrammarManifestEntry>,
+) -> Result<()> {
+    let Some(grammar_name) = &language_config.grammar else {
+        return Ok(());
+    };
+
+    if !grammars.contains_key(grammar_name) {
+        bail!(
+            "Language '{language_name}' references grammar '{grammar_name}', which is not declared in this extension's grammars"
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates a `languages/<name>` directory's `config.toml` and copies it, along with its
+/// `*.scm` queries, into `languages_pkg_dir`.
+async fn package_language(
+    language_name: &str,
+    language_src_dir: &Path,
+    grammars: &IndexMap<String, GrammarManifestEntry>,
+    languages_pkg_dir: &Path,
+) -> Result<PathBuf> {
+    let config_path = language_src_dir.join("config.toml");
+
+    let mut config_file = File::open(&config_path).await?;
+    let mut config_buffer = String::new();
+    config_file.read_to_string(&mut config_buffer).await?;
+
+    let config_toml: toml::Value = toml::from_str(&config_buffer)?;
+    validate_language_config(&serde_json::to_value(&config_toml)?)?;
+
+    let language_config: LanguageConfigToml = config_toml.try_into()?;
+    check_referenced_grammar_exists(language_name, &language_config, grammars)?;
+
+    let language_pkg_dir = languages_pkg_dir.join(language_name);
+    fs::create_dir(&language_pkg_dir).await?;
+    fs::copy(&config_path, language_pkg_dir.join("config.toml")).await?;
+
+    let mut read_dir = fs::read_dir(language_src_dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let Some(file_name) = entry.file_name().to_str().map(|name| name.to_string()) else {
             continue;
         };
 
-        if versions.contains(&extension_info.version) {
-            unpublished.push(extension_id.clone());
+        if file_name.ends_with(".scm") {
+            fs::copy(entry.path(), language_pkg_dir.join(&file_name)).await?;
         }
     }
 
+    Ok(PathBuf::from_iter(["languages", language_name]))
+}
+
+const DEFAULT_REGISTRY_BASE_URL: &str = "https://extensions.zed.dev/api";
+
+fn registry_base_url() -> String {
+    std::env::var("ZED_EXTENSIONS_REGISTRY_URL").unwrap_or_else(|_| DEFAULT_REGISTRY_BASE_URL.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct ListExtensionsResponse {
+    data: Vec<ExtensionMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtensionMetadata {
+    id: ExtensionId,
+    version: String,
+}
+
+async fn get_published_versions_by_extension_id() -> Result<HashMap<ExtensionId, Vec<String>>> {
+    let url = format!("{}/extensions", registry_base_url());
+    let response: ListExtensionsResponse = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let mut published_versions: HashMap<ExtensionId, Vec<String>> = HashMap::new();
+    for extension in response.data {
+        published_versions
+            .entry(extension.id)
+            .or_default()
+            .push(extension.version);
+    }
+
+    Ok(published_versions)
+}
+
+/// Returns the list of IDs of extensions that need to be published.
+async fn unpublished_extension_ids(extensions_toml: &ExtensionsToml) -> Result<Vec<ExtensionId>> {
+    let published_extension_versions = get_published_versions_by_extension_id().await?;
+    let unpublished = filter_unpublished(extensions_toml, &published_extension_versions);
+
     println!(
         "Extensions needing to be published: {}",
         unpublished
@@ -298,6 +693,79 @@ async fn unpublished_extension_ids(extensions_toml: &ExtensionsToml) -> Result<V
     Ok(unpublished)
 }
 
+/// Returns the IDs of extensions whose `extensions.toml` version isn't in
+/// `published_extension_versions`.
+fn filter_unpublished(
+    extensions_toml: &ExtensionsToml,
+    published_extension_versions: &HashMap<ExtensionId, Vec<String>>,
+) -> Vec<ExtensionId> {
+    extensions_toml
+        .iter()
+        .filter(|(extension_id, extension_info)| {
+            let already_published = published_extension_versions
+                .get(*extension_id)
+                .is_some_and(|versions| versions.contains(&extension_info.version));
+
+            !already_published
+        })
+        .map(|(extension_id, _)| extension_id.clone())
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+struct PublishMetadata {
+    name: String,
+    version: String,
+    schema_version: Option<u32>,
+    wasm_api_version: Option<String>,
+    authors: Vec<String>,
+    repository: Option<String>,
+    description: Option<String>,
+}
+
+/// Uploads a packaged extension's tar.gz and metadata to the registry.
+async fn publish_extension(
+    extension_id: &ExtensionId,
+    package_manifest: &ExtensionManifest,
+    archive_path: &Path,
+) -> Result<()> {
+    let archive_bytes = fs::read(archive_path).await?;
+
+    let metadata = PublishMetadata {
+        name: package_manifest.name.clone(),
+        version: package_manifest.version.clone(),
+        schema_version: package_manifest.schema_version,
+        wasm_api_version: package_manifest.wasm_api_version.clone(),
+        authors: package_manifest.authors.clone(),
+        repository: package_manifest.repository.clone(),
+        description: package_manifest.description.clone(),
+    };
+
+    let form = reqwest::multipart::Form::new()
+        .text("metadata", serde_json::to_string(&metadata)?)
+        .part(
+            "archive",
+            reqwest::multipart::Part::bytes(archive_bytes)
+                .file_name(format!("{extension_id}.tar.gz"))
+                .mime_str("application/gzip")?,
+        );
+
+    let url = format!("{}/extensions/publish", registry_base_url());
+    reqwest::Client::new()
+        .post(&url)
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    println!(
+        "Published '{extension_id}' ({})",
+        package_manifest.version
+    );
+
+    Ok(())
+}
+
 async fn changed_extension_ids(extensions_toml: &ExtensionsToml) -> Result<Vec<ExtensionId>> {
     let output = Command::new("git")
         .args(&["show", "origin/main:extensions.toml"])
@@ -330,22 +798,251 @@ async fn changed_extension_ids(extensions_toml: &ExtensionsToml) -> Result<Vec<E
     Ok(changed)
 }
 
-async fn checkout_git_repo(name: &str, repository_url: &str, commit_sha: &str) -> Result<TempDir> {
-    let repo_dir = tempfile::tempdir_in("build")?;
-
-    Command::new("git").arg("init").output().await?;
-    Command::new("git")
-        .args(&["remote", "add", "origin", repository_url])
-        .output()
-        .await?;
-    Command::new("git")
-        .args(&["fetch", "--depth", "1", "origin", commit_sha])
+async fn run_git(repo_dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_dir)
         .output()
         .await?;
-    Command::new("git")
-        .args(&["checkout", commit_sha])
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+async fn checkout_git_repo(name: &str, repository_url: &str, commit_sha: &str) -> Result<TempDir> {
+    let repo_dir = tempfile::tempdir_in(BUILD_DIR)?;
+
+    run_git(repo_dir.path(), &["init"]).await?;
+    run_git(repo_dir.path(), &["remote", "add", "origin", repository_url]).await?;
+    run_git(
+        repo_dir.path(),
+        &["fetch", "--depth", "1", "origin", commit_sha],
+    )
+    .await?;
+    run_git(repo_dir.path(), &["checkout", commit_sha]).await?;
+
+    println!("Checked out grammar '{name}' at {commit_sha}");
+
+    Ok(repo_dir)
+}
+
+const WASI_SDK_RELEASE_TAG: &str = "wasi-sdk-22";
+const WASI_SDK_VERSION: &str = "22.0";
+
+/// Downloads and caches the `wasi-sdk` clang toolchain under `cache/`.
+async fn download_wasi_sdk() -> Result<PathBuf> {
+    let sdk_dir = Path::new(CACHE_DIR).join("wasi-sdk");
+
+    if is_directory(&sdk_dir).await {
+        return Ok(sdk_dir);
+    }
+
+    let archive_url = format!(
+        "https://github.com/WebAssembly/wasi-sdk/releases/download/{WASI_SDK_RELEASE_TAG}/wasi-sdk-{WASI_SDK_VERSION}-linux.tar.gz"
+    );
+
+    let response = reqwest::get(&archive_url).await?.error_for_status()?;
+    let archive_bytes = response.bytes().await?;
+
+    let sdk_dir = tokio::task::spawn_blocking(move || -> Result<PathBuf> {
+        let decoder = flate2::read::GzDecoder::new(archive_bytes.as_ref());
+        let mut archive = tar::Archive::new(decoder);
+        archive.unpack(CACHE_DIR)?;
+
+        let unpacked_dir = Path::new(CACHE_DIR).join(format!("wasi-sdk-{WASI_SDK_VERSION}"));
+        let sdk_dir = Path::new(CACHE_DIR).join("wasi-sdk");
+        std::fs::rename(&unpacked_dir, &sdk_dir)?;
+
+        Ok(sdk_dir)
+    })
+    .await??;
+
+    Ok(sdk_dir)
+}
+
+/// Compiles a Tree-sitter grammar's parser into `<grammar-name>.wasm` under `grammars_pkg_dir`.
+async fn build_grammar(
+    grammar_name: &str,
+    grammar: &GrammarManifestEntry,
+    grammars_pkg_dir: &Path,
+) -> Result<PathBuf> {
+    let repo_dir = checkout_git_repo(grammar_name, &grammar.repository, &grammar.rev).await?;
+    let src_dir = repo_dir.path().join("src");
+
+    let mut sources = vec![src_dir.join("parser.c")];
+
+    let scanner_c_path = src_dir.join("scanner.c");
+    let scanner_cc_path = src_dir.join("scanner.cc");
+    if fs::try_exists(&scanner_c_path).await? {
+        sources.push(scanner_c_path);
+    } else if fs::try_exists(&scanner_cc_path).await? {
+        sources.push(scanner_cc_path);
+    }
+
+    let wasi_sdk_dir = download_wasi_sdk().await?;
+    let clang_path = wasi_sdk_dir.join("bin").join("clang");
+    let output_path = grammars_pkg_dir.join(grammar_name).with_extension("wasm");
+
+    let output = Command::new(clang_path)
+        .arg("--target=wasm32-wasi")
+        .arg("-shared")
+        .arg("-fPIC")
+        .arg("-Os")
+        .arg("-I")
+        .arg(&src_dir)
+        .args(&sources)
+        .arg("-o")
+        .arg(&output_path)
         .output()
         .await?;
 
-    Ok(repo_dir)
+    if !output.status.success() {
+        bail!(
+            "Failed to build grammar '{grammar_name}':\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension_id(id: &str) -> ExtensionId {
+        ExtensionId(id.to_string())
+    }
+
+    fn extension_info(version: &str) -> ExtensionInfo {
+        ExtensionInfo {
+            path: PathBuf::from("extensions/test"),
+            version: version.to_string(),
+        }
+    }
+
+    fn base_manifest() -> ExtensionManifest {
+        ExtensionManifest {
+            name: "test".to_string(),
+            version: "0.1.0".to_string(),
+            description: None,
+            repository: None,
+            authors: Vec::new(),
+            schema_version: None,
+            min_schema_version: None,
+            max_schema_version: None,
+            wasm_api_version: None,
+            lib: None,
+            themes: Vec::new(),
+            languages: Vec::new(),
+            grammars: IndexMap::new(),
+            language_servers: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn filter_unpublished_excludes_already_published_versions() {
+        let mut extensions = IndexMap::new();
+        extensions.insert(extension_id("foo"), extension_info("1.0.0"));
+        extensions.insert(extension_id("bar"), extension_info("2.0.0"));
+        let extensions_toml = ExtensionsToml(extensions);
+
+        let mut published_extension_versions = HashMap::new();
+        published_extension_versions.insert(extension_id("foo"), vec!["1.0.0".to_string()]);
+
+        let unpublished = filter_unpublished(&extensions_toml, &published_extension_versions);
+
+        assert_eq!(unpublished, vec![extension_id("bar")]);
+    }
+
+    #[test]
+    fn filter_unpublished_includes_newer_versions_of_published_extensions() {
+        let mut extensions = IndexMap::new();
+        extensions.insert(extension_id("foo"), extension_info("1.1.0"));
+        let extensions_toml = ExtensionsToml(extensions);
+
+        let mut published_extension_versions = HashMap::new();
+        published_extension_versions.insert(extension_id("foo"), vec!["1.0.0".to_string()]);
+
+        let unpublished = filter_unpublished(&extensions_toml, &published_extension_versions);
+
+        assert_eq!(unpublished, vec![extension_id("foo")]);
+    }
+
+    #[test]
+    fn check_schema_version_compatibility_accepts_missing_constraints() {
+        let metadata = base_manifest();
+
+        assert!(check_schema_version_compatibility(&metadata).is_ok());
+    }
+
+    #[test]
+    fn check_schema_version_compatibility_rejects_newer_schema_version() {
+        let metadata = ExtensionManifest {
+            schema_version: Some(CURRENT_SCHEMA_VERSION + 1),
+            ..base_manifest()
+        };
+
+        assert!(check_schema_version_compatibility(&metadata).is_err());
+    }
+
+    #[test]
+    fn check_schema_version_compatibility_rejects_unreachable_min_schema_version() {
+        let metadata = ExtensionManifest {
+            min_schema_version: Some(CURRENT_SCHEMA_VERSION + 1),
+            ..base_manifest()
+        };
+
+        assert!(check_schema_version_compatibility(&metadata).is_err());
+    }
+
+    #[test]
+    fn check_schema_version_compatibility_rejects_unreachable_max_schema_version() {
+        let metadata = ExtensionManifest {
+            max_schema_version: Some(0),
+            ..base_manifest()
+        };
+
+        assert!(check_schema_version_compatibility(&metadata).is_err());
+    }
+
+    #[test]
+    fn check_referenced_grammar_exists_accepts_no_grammar_reference() {
+        let language_config = LanguageConfigToml { grammar: None };
+
+        assert!(check_referenced_grammar_exists("rust", &language_config, &IndexMap::new()).is_ok());
+    }
+
+    #[test]
+    fn check_referenced_grammar_exists_accepts_declared_grammar() {
+        let language_config = LanguageConfigToml {
+            grammar: Some("rust".to_string()),
+        };
+        let mut grammars = IndexMap::new();
+        grammars.insert(
+            "rust".to_string(),
+            GrammarManifestEntry {
+                repository: "https://github.com/tree-sitter/tree-sitter-rust".to_string(),
+                rev: "abc123".to_string(),
+            },
+        );
+
+        assert!(check_referenced_grammar_exists("rust", &language_config, &grammars).is_ok());
+    }
+
+    #[test]
+    fn check_referenced_grammar_exists_rejects_undeclared_grammar() {
+        let language_config = LanguageConfigToml {
+            grammar: Some("rust".to_string()),
+        };
+
+        assert!(check_referenced_grammar_exists("rust", &language_config, &IndexMap::new()).is_err());
+    }
 }